@@ -0,0 +1,269 @@
+//! Replay-and-benchmark harness driven by workload files.
+//!
+//! A [`Workload`] names one or more recorded rollout `.jsonl` files and how
+//! to replay them; [`run_workload`] drives each through
+//! [`RolloutRecorder::get_rollout_history`] and back out through a real
+//! [`rollout_writer`](super::recorder::rollout_writer) task fed over its own
+//! [`RolloutCmd`](super::recorder::RolloutCmd) channel, so the reported
+//! latencies are the same batched, tranquilizer-throttled flushes a live
+//! session would produce rather than one fsync per record, and reports
+//! structured, JSON-serializable timings so CI can track regressions in the
+//! batching and throttling behavior in [`super::recorder`] against real
+//! captured sessions rather than synthetic data. Sessions within a workload
+//! are independent, so they are replayed with up to `workload.concurrency`
+//! running at once.
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use futures::stream::StreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+
+use super::recorder::BatchConfig;
+use super::recorder::RolloutCmd;
+use super::recorder::RolloutItem;
+use super::recorder::RolloutRecorder;
+use super::recorder::rollout_writer;
+use super::transform::RolloutTransformChain;
+use crate::conversation_manager::InitialHistory;
+
+/// Batch thresholds this harness drives `rollout_writer` with. These mirror
+/// the kind of values `Config::rollout_batch_*` would supply in production;
+/// the harness has no `Config` of its own to read them from, so they are
+/// fixed here and passed to `BatchConfig::new` instead of
+/// `BatchConfig::from_config`.
+const REPLAY_MAX_BYTES: usize = 64 * 1024;
+const REPLAY_MIN_LATENCY: Duration = Duration::from_millis(50);
+const REPLAY_MAX_LATENCY: Duration = Duration::from_millis(1000);
+
+fn default_iterations() -> usize {
+    1
+}
+
+fn default_concurrency() -> usize {
+    1
+}
+
+/// A reproducible workload: which recorded sessions to replay and how.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Workload {
+    pub sessions: Vec<PathBuf>,
+    #[serde(default = "default_iterations")]
+    pub iterations: usize,
+    /// How many sessions to replay concurrently. Sessions within a single
+    /// workload are independent, so this only bounds parallelism.
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// If true, re-drive the model for each replayed item instead of just
+    /// re-serializing the recorded stream. Not yet wired up: no model
+    /// client is reachable from this crate, so `run_workload` returns an
+    /// error rather than silently falling back to re-serialization.
+    #[serde(default)]
+    pub redrive_model: bool,
+}
+
+/// Replay results for a single session in a [`Workload`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SessionReplayResult {
+    pub path: PathBuf,
+    pub records_replayed: u64,
+    pub records_per_sec: f64,
+    pub bytes_written: u64,
+    pub p50_latency_ms: f64,
+    pub p99_latency_ms: f64,
+    pub wall_clock_ms: f64,
+}
+
+/// Structured results for an entire [`Workload`], serialized as JSON by the
+/// caller so it can be diffed across commits.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WorkloadResult {
+    pub sessions: Vec<SessionReplayResult>,
+}
+
+/// Reads `workload.sessions`, replays each `workload.iterations` times, and
+/// reports per-session throughput and latency percentiles. Up to
+/// `workload.concurrency` sessions are replayed at once.
+pub async fn run_workload(workload: &Workload) -> std::io::Result<WorkloadResult> {
+    if workload.redrive_model {
+        return Err(std::io::Error::other(
+            "redrive_model replay is not supported: no model client is reachable from codex-core",
+        ));
+    }
+
+    let concurrency = workload.concurrency.max(1);
+    let iterations = workload.iterations.max(1);
+    let sessions = futures::stream::iter(workload.sessions.iter())
+        .map(|path| async move { replay_session(path, iterations).await })
+        .buffer_unordered(concurrency)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    Ok(WorkloadResult { sessions })
+}
+
+/// Replays `path` by reading its recorded history and feeding it straight
+/// into a real [`rollout_writer`] task over its own command channel, so the
+/// measured latencies are actual batched flush durations rather than
+/// per-record serialization cost.
+async fn replay_session(path: &Path, iterations: usize) -> std::io::Result<SessionReplayResult> {
+    let mut latencies = Vec::new();
+    let mut records_replayed: u64 = 0;
+    let mut bytes_written: u64 = 0;
+    let started = Instant::now();
+
+    for _ in 0..iterations {
+        let items = match RolloutRecorder::get_rollout_history(path).await? {
+            InitialHistory::Resumed(items) => items,
+            InitialHistory::New => Vec::new(),
+        };
+
+        let scratch_path = scratch_path_for(path);
+        let scratch_file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&scratch_path)
+            .await?;
+
+        let (cmd_tx, cmd_rx) = mpsc::channel::<RolloutCmd>(256);
+        let (flush_tx, mut flush_rx) = mpsc::unbounded_channel::<Duration>();
+        let batch = BatchConfig::new(REPLAY_MAX_BYTES, REPLAY_MIN_LATENCY, REPLAY_MAX_LATENCY);
+        let writer_task = tokio::task::spawn(rollout_writer(
+            scratch_file,
+            cmd_rx,
+            None,
+            None,
+            std::env::temp_dir(),
+            RolloutTransformChain::default(),
+            batch,
+            scratch_path.clone(),
+            Some(flush_tx),
+        ));
+
+        for item in items {
+            cmd_tx
+                .send(cmd_for_replay(item))
+                .await
+                .map_err(|e| std::io::Error::other(format!("failed to queue replay record: {e}")))?;
+            records_replayed += 1;
+        }
+
+        let (ack_tx, ack_rx) = oneshot::channel();
+        cmd_tx
+            .send(RolloutCmd::Shutdown { ack: ack_tx })
+            .await
+            .map_err(|e| std::io::Error::other(format!("failed to queue replay shutdown: {e}")))?;
+        ack_rx.await.map_err(|e| {
+            std::io::Error::other(format!("replay writer dropped before acking shutdown: {e}"))
+        })?;
+        writer_task
+            .await
+            .map_err(|e| std::io::Error::other(format!("replay writer task panicked: {e}")))??;
+
+        while let Ok(duration) = flush_rx.try_recv() {
+            latencies.push(duration);
+        }
+        bytes_written += tokio::fs::metadata(&scratch_path).await?.len();
+
+        let _ = tokio::fs::remove_file(&scratch_path).await;
+    }
+
+    let wall_clock = started.elapsed();
+    latencies.sort_unstable();
+    let records_per_sec = if wall_clock.as_secs_f64() > 0.0 {
+        records_replayed as f64 / wall_clock.as_secs_f64()
+    } else {
+        0.0
+    };
+
+    Ok(SessionReplayResult {
+        path: path.to_path_buf(),
+        records_replayed,
+        records_per_sec,
+        bytes_written,
+        p50_latency_ms: percentile(&latencies, 0.50).as_secs_f64() * 1000.0,
+        p99_latency_ms: percentile(&latencies, 0.99).as_secs_f64() * 1000.0,
+        wall_clock_ms: wall_clock.as_secs_f64() * 1000.0,
+    })
+}
+
+/// Maps a recorded [`RolloutItem`] onto the [`RolloutCmd`] a live session
+/// would have sent to produce it, so replay exercises the exact same
+/// command path `RolloutRecorder` uses.
+fn cmd_for_replay(item: RolloutItem) -> RolloutCmd {
+    match item {
+        RolloutItem::ResponseItem(item) => RolloutCmd::AddResponseItems(vec![item]),
+        RolloutItem::Event(event) => RolloutCmd::AddEvents(vec![event]),
+        RolloutItem::SessionMeta(meta) => RolloutCmd::AddSessionMeta(meta),
+    }
+}
+
+/// A scratch file under the system temp directory used to measure real
+/// flush latency without mutating the session being replayed.
+fn scratch_path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("session");
+    std::env::temp_dir().join(format!("codex-rollout-bench-{}-{file_name}", uuid::Uuid::new_v4()))
+}
+
+fn percentile(sorted_latencies: &[Duration], p: f64) -> Duration {
+    if sorted_latencies.is_empty() {
+        return Duration::ZERO;
+    }
+    let idx = (((sorted_latencies.len() - 1) as f64) * p).round() as usize;
+    sorted_latencies[idx.min(sorted_latencies.len() - 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use codex_protocol::models::ContentItem;
+    use codex_protocol::models::ResponseItem;
+
+    use super::*;
+
+    #[test]
+    fn percentile_of_empty_latencies_is_zero() {
+        assert_eq!(percentile(&[], 0.50), Duration::ZERO);
+    }
+
+    #[test]
+    fn percentile_picks_the_nearest_ranked_sample() {
+        let sorted = vec![
+            Duration::from_millis(1),
+            Duration::from_millis(2),
+            Duration::from_millis(3),
+            Duration::from_millis(4),
+            Duration::from_millis(5),
+        ];
+
+        assert_eq!(percentile(&sorted, 0.0), Duration::from_millis(1));
+        assert_eq!(percentile(&sorted, 1.0), Duration::from_millis(5));
+        assert_eq!(percentile(&sorted, 0.50), Duration::from_millis(3));
+    }
+
+    #[test]
+    fn cmd_for_replay_maps_a_response_item_onto_add_response_items() {
+        let response = RolloutItem::ResponseItem(ResponseItem::Message {
+            id: None,
+            role: "user".to_string(),
+            content: vec![ContentItem::InputText {
+                text: "hi".to_string(),
+            }],
+        });
+
+        match cmd_for_replay(response) {
+            RolloutCmd::AddResponseItems(items) => assert_eq!(items.len(), 1),
+            _ => panic!("expected AddResponseItems"),
+        }
+    }
+}
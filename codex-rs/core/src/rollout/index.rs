@@ -0,0 +1,185 @@
+//! Sidecar index for O(1) conversation listing and session stats.
+//!
+//! `rollout_writer` updates `<rollout>.idx.json` every time it flushes, so
+//! `list_conversations` and [`RolloutRecorder::stats`](super::recorder::RolloutRecorder::stats)
+//! never need to parse the full `.jsonl` unless the sidecar is missing or
+//! stale (e.g. written by an older Codex version).
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::recorder::SessionMetaWithGit;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SidecarIndex {
+    pub session_meta: Option<SessionMetaWithGit>,
+    pub first_timestamp: Option<String>,
+    pub last_timestamp: Option<String>,
+    /// Number of records written so far, keyed by record type
+    /// (`"response"`, `"tool_call"`, `"event"`, `"session_meta"`, ...).
+    pub record_counts: BTreeMap<String, u64>,
+    /// Byte offset into the `.jsonl` of the start of each record, in write
+    /// order, so a reader can seek directly to a notable record instead of
+    /// scanning from the top.
+    pub offsets: Vec<u64>,
+    /// Total bytes written to the rollout file so far.
+    pub total_bytes: u64,
+}
+
+impl SidecarIndex {
+    /// Records that a line of `len` bytes was just written at `offset`.
+    pub fn record(&mut self, record_type: &str, offset: u64, len: u64, timestamp: Option<&str>) {
+        *self
+            .record_counts
+            .entry(record_type.to_string())
+            .or_insert(0) += 1;
+        self.offsets.push(offset);
+        self.total_bytes += len;
+        if let Some(ts) = timestamp {
+            if self.first_timestamp.is_none() {
+                self.first_timestamp = Some(ts.to_string());
+            }
+            self.last_timestamp = Some(ts.to_string());
+        }
+    }
+}
+
+/// Maps `rollout-2025-05-07T17-24-21-<uuid>.jsonl` to its sidecar
+/// `rollout-2025-05-07T17-24-21-<uuid>.idx.json`.
+pub fn index_path_for(rollout_path: &Path) -> PathBuf {
+    let file_name = rollout_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    let idx_name = format!("{}.idx.json", file_name.trim_end_matches(".jsonl"));
+    rollout_path.with_file_name(idx_name)
+}
+
+/// Reads the sidecar index for `rollout_path`, if one exists.
+pub async fn read_index(rollout_path: &Path) -> std::io::Result<Option<SidecarIndex>> {
+    let idx_path = index_path_for(rollout_path);
+    match tokio::fs::read(&idx_path).await {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| std::io::Error::other(format!("invalid sidecar index {idx_path:?}: {e}"))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Overwrites the sidecar index for `rollout_path` with `index`.
+pub async fn write_index(rollout_path: &Path, index: &SidecarIndex) -> std::io::Result<()> {
+    let idx_path = index_path_for(rollout_path);
+    let json = serde_json::to_vec(index)?;
+    tokio::fs::write(idx_path, json).await
+}
+
+/// Whether `index` is out of date with respect to `rollout_path`'s current
+/// size, e.g. because the file was written by an older Codex version that
+/// didn't maintain a sidecar, or a write landed after the index was last
+/// saved. Callers should treat a stale index the same as a missing one and
+/// fall back to a full parse.
+pub async fn index_is_stale(rollout_path: &Path, index: &SidecarIndex) -> std::io::Result<bool> {
+    let actual_len = tokio::fs::metadata(rollout_path).await?.len();
+    Ok(actual_len != index.total_bytes)
+}
+
+/// Aggregate counts returned by `RolloutRecorder::stats`, cheap to compute
+/// from a [`SidecarIndex`] without touching the `.jsonl` itself.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RolloutStats {
+    pub response_items: u64,
+    pub events: u64,
+    pub tool_calls: u64,
+    pub total_bytes: u64,
+}
+
+impl From<&SidecarIndex> for RolloutStats {
+    fn from(index: &SidecarIndex) -> Self {
+        Self {
+            response_items: *index.record_counts.get("response").unwrap_or(&0),
+            events: *index.record_counts.get("event").unwrap_or(&0),
+            tool_calls: *index.record_counts.get("tool_call").unwrap_or(&0),
+            total_bytes: index.total_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_rollout_path() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("codex-rollout-index-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("rollout-test.jsonl")
+    }
+
+    #[test]
+    fn index_path_for_swaps_extension_for_idx_json() {
+        let path = Path::new("/home/user/.codex/sessions/rollout-2025-05-07T17-24-21-uuid.jsonl");
+
+        let idx_path = index_path_for(path);
+
+        assert_eq!(
+            idx_path,
+            Path::new("/home/user/.codex/sessions/rollout-2025-05-07T17-24-21-uuid.idx.json")
+        );
+    }
+
+    #[tokio::test]
+    async fn read_index_returns_none_when_sidecar_is_missing() {
+        let path = temp_rollout_path();
+
+        let index = read_index(&path).await.unwrap();
+
+        assert!(index.is_none());
+        let _ = tokio::fs::remove_dir_all(path.parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn write_then_read_index_round_trips() {
+        let path = temp_rollout_path();
+        let mut index = SidecarIndex::default();
+        index.record("response", 0, 12, None);
+
+        write_index(&path, &index).await.unwrap();
+        let read_back = read_index(&path).await.unwrap().unwrap();
+
+        assert_eq!(read_back.total_bytes, 12);
+        assert_eq!(read_back.record_counts.get("response"), Some(&1));
+        let _ = tokio::fs::remove_dir_all(path.parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn index_is_stale_when_file_size_diverges_from_the_index() {
+        let path = temp_rollout_path();
+        tokio::fs::write(&path, b"line one\nline two\n").await.unwrap();
+        let mut index = SidecarIndex::default();
+        index.record("response", 0, 9, None);
+        // The index only accounts for the first line; the file has a second
+        // line the index was never updated for.
+
+        let stale = index_is_stale(&path, &index).await.unwrap();
+
+        assert!(stale);
+        let _ = tokio::fs::remove_dir_all(path.parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn index_is_fresh_when_file_size_matches_the_index() {
+        let path = temp_rollout_path();
+        tokio::fs::write(&path, b"line one\n").await.unwrap();
+        let mut index = SidecarIndex::default();
+        index.record("response", 0, 9, None);
+
+        let stale = index_is_stale(&path, &index).await.unwrap();
+
+        assert!(!stale);
+        let _ = tokio::fs::remove_dir_all(path.parent().unwrap()).await;
+    }
+}
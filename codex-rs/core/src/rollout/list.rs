@@ -0,0 +1,276 @@
+//! Paginated conversation listing, backed by the sidecar index where
+//! possible.
+//!
+//! [`get_conversations`] walks `<codex_home>/SESSIONS_SUBDIR/YYYY/MM/DD` for
+//! rollout files (the layout [`super::recorder::create_log_file`] writes)
+//! and, for each candidate, builds a [`ConversationSummary`] from its
+//! `.idx.json` sidecar rather than parsing the full `.jsonl` — falling back
+//! to a full parse only when the sidecar is missing or
+//! [`index_is_stale`](super::index::index_is_stale). Directory discovery
+//! itself is still `O(files)`, but per-file work drops from "read and parse
+//! the whole rollout" to "read one small JSON sidecar".
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::SESSIONS_SUBDIR;
+use super::index::RolloutStats;
+use super::index::SidecarIndex;
+use super::index::index_is_stale;
+use super::index::read_index;
+use super::recorder::RolloutItem;
+use super::recorder::RolloutRecorder;
+use super::recorder::SessionMetaWithGit;
+use crate::conversation_manager::InitialHistory;
+
+/// Opaque resume point for [`get_conversations`], wrapping the filename of
+/// the last conversation returned. Rollout filenames sort lexicographically
+/// in the same order as their creation timestamp (see `create_log_file`), so
+/// comparing them is enough to resume a descending walk without re-scanning
+/// earlier pages.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Cursor {
+    last_file_name: String,
+}
+
+/// Summary of one rollout file, cheap enough to build for every conversation
+/// on a page.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConversationSummary {
+    pub path: PathBuf,
+    pub session_meta: Option<SessionMetaWithGit>,
+    pub first_timestamp: Option<String>,
+    pub last_timestamp: Option<String>,
+    pub stats: RolloutStats,
+}
+
+/// One page of [`get_conversations`] results, newest conversation first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConversationsPage {
+    pub items: Vec<ConversationSummary>,
+    /// `Some` if there may be more conversations older than `items.last()`.
+    pub next_cursor: Option<Cursor>,
+}
+
+/// Lists up to `page_size` conversations under `codex_home`, newest first,
+/// resuming after `cursor` if given.
+pub async fn get_conversations(
+    codex_home: &Path,
+    page_size: usize,
+    cursor: Option<&Cursor>,
+) -> std::io::Result<ConversationsPage> {
+    let mut rollout_paths = discover_rollout_files(codex_home).await?;
+    // Descending by filename == descending by creation time.
+    rollout_paths.sort_unstable_by(|a, b| b.cmp(a));
+
+    let (candidates, next_cursor) = paginate(&rollout_paths, page_size, cursor);
+
+    let mut items = Vec::with_capacity(candidates.len());
+    for file_name in candidates {
+        let path = rollout_path_for(codex_home, file_name)?;
+        items.push(summarize_conversation(&path).await?);
+    }
+
+    Ok(ConversationsPage { items, next_cursor })
+}
+
+/// Pure pagination over an already-sorted (descending) list of rollout file
+/// names, split out from [`get_conversations`] so the cursor-skip and
+/// next-cursor math can be unit tested without touching the filesystem.
+fn paginate<'a>(
+    sorted_file_names: &'a [String],
+    page_size: usize,
+    cursor: Option<&Cursor>,
+) -> (&'a [String], Option<Cursor>) {
+    let candidates = match cursor {
+        Some(cursor) => {
+            let skip = sorted_file_names
+                .iter()
+                .take_while(|name| name.as_str() >= cursor.last_file_name.as_str())
+                .count();
+            &sorted_file_names[skip..]
+        }
+        None => sorted_file_names,
+    };
+
+    let page = &candidates[..page_size.min(candidates.len())];
+    let next_cursor = if candidates.len() > page_size {
+        candidates.get(page_size - 1).map(|last| Cursor {
+            last_file_name: last.clone(),
+        })
+    } else {
+        None
+    };
+
+    (page, next_cursor)
+}
+
+/// Builds a [`ConversationSummary`] for `path` from its sidecar index when
+/// present and fresh, falling back to a full parse otherwise.
+async fn summarize_conversation(path: &Path) -> std::io::Result<ConversationSummary> {
+    if let Some(index) = read_index(path).await? {
+        if !index_is_stale(path, &index).await? {
+            return Ok(summary_from_index(path, &index));
+        }
+    }
+
+    let items = match RolloutRecorder::get_rollout_history(path).await? {
+        InitialHistory::Resumed(items) => items,
+        InitialHistory::New => Vec::new(),
+    };
+    let stats = RolloutRecorder::stats(path).await?;
+    let session_meta = items.into_iter().find_map(|item| match item {
+        RolloutItem::SessionMeta(meta) => Some(meta),
+        _ => None,
+    });
+    let first_timestamp = session_meta.as_ref().map(|m| m.timestamp().to_string());
+    let last_timestamp = first_timestamp.clone();
+
+    Ok(ConversationSummary {
+        path: path.to_path_buf(),
+        session_meta,
+        first_timestamp,
+        last_timestamp,
+        stats,
+    })
+}
+
+fn summary_from_index(path: &Path, index: &SidecarIndex) -> ConversationSummary {
+    ConversationSummary {
+        path: path.to_path_buf(),
+        session_meta: index.session_meta.clone(),
+        first_timestamp: index.first_timestamp.clone(),
+        last_timestamp: index.last_timestamp.clone(),
+        stats: RolloutStats::from(index),
+    }
+}
+
+/// Reconstructs the full path to `file_name` from `codex_home`, under the
+/// `YYYY/MM/DD` directory `create_log_file` writes it to, taking the date
+/// from the file name itself. The path is not checked for existence.
+fn rollout_path_for(codex_home: &Path, file_name: &str) -> std::io::Result<PathBuf> {
+    // `discover_rollout_files` already visited every `YYYY/MM/DD` directory
+    // once; re-deriving the date from the filename instead of walking again
+    // keeps this O(1) per lookup. `create_log_file` embeds it right after
+    // the `rollout-` prefix as `YYYY-MM-DDThh-mm-ss`.
+    let date = file_name
+        .strip_prefix("rollout-")
+        .and_then(|rest| rest.get(0..10))
+        .ok_or_else(|| {
+            std::io::Error::other(format!("unrecognized rollout file name: {file_name}"))
+        })?;
+    let year = &date[0..4];
+    let month = &date[5..7];
+    let day = &date[8..10];
+
+    let mut path = codex_home.to_path_buf();
+    path.push(SESSIONS_SUBDIR);
+    path.push(year);
+    path.push(month);
+    path.push(day);
+    path.push(file_name);
+    Ok(path)
+}
+
+/// Walks `<codex_home>/SESSIONS_SUBDIR/YYYY/MM/DD` and returns the bare file
+/// names of every rollout `.jsonl` found, in no particular order.
+async fn discover_rollout_files(codex_home: &Path) -> std::io::Result<Vec<String>> {
+    let mut sessions_dir = codex_home.to_path_buf();
+    sessions_dir.push(SESSIONS_SUBDIR);
+
+    let mut file_names = Vec::new();
+    for year_dir in read_subdirs(&sessions_dir).await? {
+        for month_dir in read_subdirs(&year_dir).await? {
+            for day_dir in read_subdirs(&month_dir).await? {
+                let mut entries = match tokio::fs::read_dir(&day_dir).await {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e),
+                };
+                while let Some(entry) = entries.next_entry().await? {
+                    if let Some(name) = entry.file_name().to_str() {
+                        if name.starts_with("rollout-") && name.ends_with(".jsonl") {
+                            file_names.push(name.to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(file_names)
+}
+
+async fn read_subdirs(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut dirs = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    Ok(dirs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn first_page_with_no_cursor_returns_the_newest_entries_and_a_next_cursor() {
+        let sorted = names(&["c", "b", "a"]);
+
+        let (page, next_cursor) = paginate(&sorted, 2, None);
+
+        assert_eq!(page, &["c".to_string(), "b".to_string()]);
+        assert_eq!(
+            next_cursor,
+            Some(Cursor {
+                last_file_name: "b".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn cursor_resumes_strictly_after_the_last_returned_entry() {
+        let sorted = names(&["c", "b", "a"]);
+        let cursor = Cursor {
+            last_file_name: "b".to_string(),
+        };
+
+        let (page, next_cursor) = paginate(&sorted, 2, Some(&cursor));
+
+        assert_eq!(page, &["a".to_string()]);
+        assert_eq!(next_cursor, None, "only one entry left, no further page");
+    }
+
+    #[test]
+    fn exact_final_page_reports_no_next_cursor() {
+        let sorted = names(&["c", "b"]);
+
+        let (page, next_cursor) = paginate(&sorted, 2, None);
+
+        assert_eq!(page.len(), 2);
+        assert_eq!(next_cursor, None);
+    }
+
+    #[test]
+    fn empty_candidate_list_returns_an_empty_page() {
+        let sorted: Vec<String> = Vec::new();
+
+        let (page, next_cursor) = paginate(&sorted, 10, None);
+
+        assert!(page.is_empty());
+        assert_eq!(next_cursor, None);
+    }
+}
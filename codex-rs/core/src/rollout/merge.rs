@@ -0,0 +1,435 @@
+//! Reconciles two rollout files that share a session but diverged — e.g.
+//! the same conversation resumed on two machines, or a crash left a
+//! partially-synced copy.
+//!
+//! Both files are treated as append-only logs. Records before the point
+//! where they diverge are taken as shared history; everything after is
+//! unioned and keyed by a stable per-record identity (a response item's
+//! `call_id`/`id`, or an event's `id`), falling back to a content hash for
+//! records that carry none (e.g. a plain `ResponseItem::Message`, which has
+//! `id: None` for ordinary turns) so that two distinct id-less records
+//! appended at the same post-divergence position are never mistaken for the
+//! same record. Duplicates by identity collapse; records with the same
+//! identity but differing bodies are reported as [`MergeConflict`]s rather
+//! than silently dropped, and `SessionMeta` is resolved with last-writer-wins,
+//! the loser retained as a `prev_session_meta` line.
+//!
+//! None of the body record types in this protocol carry a timestamp (only
+//! `SessionMeta` does), so the unioned tail is ordered by each record's
+//! offset from the divergence point, interleaving the two files' tails
+//! instead of appending one after the other; a record that wins a
+//! last-writer-wins conflict is re-positioned to its winning copy's offset.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use codex_protocol::models::ResponseItem;
+use codex_protocol::protocol::Event;
+
+use super::recorder::RolloutItem;
+use super::recorder::RolloutRecorder;
+use super::recorder::SessionMetaWithGit;
+use crate::conversation_manager::InitialHistory;
+
+/// Stable key used to identify the same logical record across two diverged
+/// files.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum RecordIdentity {
+    ResponseItem(String),
+    Event(String),
+    /// Fallback for records with no embedded id, keyed by a hash of their
+    /// serialized body rather than their position in the stream.
+    Content(u64),
+}
+
+/// A record-identity collision where the two files disagree on the body.
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeConflict {
+    pub identity: String,
+    pub a: Value,
+    pub b: Value,
+}
+
+/// Result of [`merge`]: the reconciled item stream plus any conflicts found
+/// along the way.
+#[derive(Debug, Clone, Default)]
+pub struct MergedHistory {
+    pub items: Vec<RolloutItem>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergedHistory {
+    /// Writes the reconciled stream as a new well-formed rollout file at
+    /// `path`. If any conflicts were found, they are written alongside it
+    /// as `<path>.conflicts.json` rather than dropped.
+    pub async fn write_to(&self, path: &Path) -> std::io::Result<()> {
+        let mut buffer = Vec::new();
+        let mut wrote_session_meta = false;
+        for item in &self.items {
+            match item {
+                RolloutItem::SessionMeta(meta) => {
+                    let record_type = if wrote_session_meta {
+                        "prev_session_meta"
+                    } else {
+                        "session_meta"
+                    };
+                    wrote_session_meta = true;
+                    write_meta_line(&mut buffer, record_type, meta)?;
+                }
+                RolloutItem::ResponseItem(item) => {
+                    serde_json::to_writer(&mut buffer, item)?;
+                    buffer.push(b'\n');
+                }
+                RolloutItem::Event(event) => write_event_line(&mut buffer, event)?,
+            }
+        }
+        tokio::fs::write(path, buffer).await?;
+
+        if !self.conflicts.is_empty() {
+            let conflicts_path = conflicts_path_for(path);
+            let json = serde_json::to_vec_pretty(&self.conflicts)?;
+            tokio::fs::write(conflicts_path, json).await?;
+        }
+        Ok(())
+    }
+}
+
+fn conflicts_path_for(rollout_path: &Path) -> std::path::PathBuf {
+    let file_name = rollout_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    rollout_path.with_file_name(format!("{}.conflicts.json", file_name.trim_end_matches(".jsonl")))
+}
+
+#[derive(Serialize)]
+struct MetaLine<'a> {
+    record_type: &'static str,
+    #[serde(flatten)]
+    meta: &'a SessionMetaWithGit,
+}
+
+fn write_meta_line(
+    buffer: &mut Vec<u8>,
+    record_type: &'static str,
+    meta: &SessionMetaWithGit,
+) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *buffer, &MetaLine { record_type, meta })?;
+    buffer.push(b'\n');
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct EventLine<'a> {
+    record_type: &'static str,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+fn write_event_line(buffer: &mut Vec<u8>, event: &Event) -> std::io::Result<()> {
+    serde_json::to_writer(
+        &mut *buffer,
+        &EventLine {
+            record_type: "event",
+            event,
+        },
+    )?;
+    buffer.push(b'\n');
+    Ok(())
+}
+
+/// Reconciles `a` and `b` into a single [`MergedHistory`].
+pub async fn merge(a: &Path, b: &Path) -> std::io::Result<MergedHistory> {
+    let (meta_a, body_a) = split_meta(read_items(a).await?);
+    let (meta_b, body_b) = split_meta(read_items(b).await?);
+
+    let (merged, conflicts) = reconcile_bodies(&body_a, &body_b);
+
+    let mut items = resolve_session_meta(meta_a, meta_b);
+    items.extend(merged);
+
+    Ok(MergedHistory { items, conflicts })
+}
+
+/// Pure reconciliation of two diverged body-record streams (no `SessionMeta`,
+/// no file I/O), split out from [`merge`] so the identity-collision and
+/// tail-ordering logic can be unit tested directly against in-memory
+/// fixtures.
+fn reconcile_bodies(
+    body_a: &[RolloutItem],
+    body_b: &[RolloutItem],
+) -> (Vec<RolloutItem>, Vec<MergeConflict>) {
+    let common_len = body_a
+        .iter()
+        .zip(body_b.iter())
+        .take_while(|(x, y)| items_equal(x, y))
+        .count();
+
+    let mut merged: Vec<RolloutItem> = body_a[..common_len].to_vec();
+    // Parallel to `merged`: for tail entries, the (file_index, tail_offset)
+    // of the copy currently occupying that slot, used to order the tail
+    // chronologically (by divergence-relative offset) rather than by which
+    // file happened to be processed first.
+    let mut tail_origin: Vec<Option<(usize, u64)>> = vec![None; merged.len()];
+    let mut seen: HashMap<RecordIdentity, usize> = HashMap::new();
+    for (offset, item) in merged.iter().enumerate() {
+        seen.insert(identity_for(item), offset);
+    }
+
+    let mut conflicts = Vec::new();
+    for (file_index, tail) in [(0usize, &body_a[common_len..]), (1usize, &body_b[common_len..])] {
+        for (tail_offset, item) in tail.iter().enumerate() {
+            let tail_offset = tail_offset as u64;
+            let identity = identity_for(item);
+            match seen.get(&identity) {
+                Some(&existing_idx) => {
+                    if !items_equal(&merged[existing_idx], item) {
+                        conflicts.push(MergeConflict {
+                            identity: format!("{identity:?}"),
+                            a: to_value(&merged[existing_idx]),
+                            b: to_value(item),
+                        });
+                        // Last-writer-wins: whichever of the two diverged
+                        // tails supplies the later value for this identity
+                        // wins; `b` (file_index 1) is treated as the more
+                        // recent copy when both tails define it.
+                        if file_index == 1 {
+                            merged[existing_idx] = item.clone();
+                            tail_origin[existing_idx] = Some((file_index, tail_offset));
+                        }
+                    }
+                }
+                None => {
+                    seen.insert(identity, merged.len());
+                    merged.push(item.clone());
+                    tail_origin.push(Some((file_index, tail_offset)));
+                }
+            }
+        }
+    }
+
+    // Neither `ResponseItem` nor `Event` carries a timestamp in this
+    // protocol, so order the unioned tail by its divergence-relative offset
+    // (with file index as a tiebreak) rather than leaving it as "all of A's
+    // new records, then all of B's".
+    let prefix_len = common_len.min(merged.len());
+    let mut tail: Vec<(RolloutItem, Option<(usize, u64)>)> = merged
+        .split_off(prefix_len)
+        .into_iter()
+        .zip(tail_origin.split_off(prefix_len))
+        .collect();
+    tail.sort_by_key(|(_, origin)| origin.map(|(file_index, offset)| (offset, file_index)));
+    merged.extend(tail.into_iter().map(|(item, _)| item));
+
+    (merged, conflicts)
+}
+
+impl RolloutRecorder {
+    /// Reconciles two rollout files that share a session but diverged. See
+    /// the [module docs](self) for the merge semantics.
+    pub async fn merge(a: &Path, b: &Path) -> std::io::Result<MergedHistory> {
+        merge(a, b).await
+    }
+}
+
+async fn read_items(path: &Path) -> std::io::Result<Vec<RolloutItem>> {
+    match RolloutRecorder::get_rollout_history(path).await? {
+        InitialHistory::Resumed(items) => Ok(items),
+        InitialHistory::New => Ok(Vec::new()),
+    }
+}
+
+fn split_meta(items: Vec<RolloutItem>) -> (Vec<SessionMetaWithGit>, Vec<RolloutItem>) {
+    let mut metas = Vec::new();
+    let mut body = Vec::new();
+    for item in items {
+        match item {
+            RolloutItem::SessionMeta(meta) => metas.push(meta),
+            other => body.push(other),
+        }
+    }
+    (metas, body)
+}
+
+/// Resolves the two files' `SessionMeta` lineages with last-writer-wins on
+/// `timestamp`, keeping the loser as a `prev_session_meta` entry so its
+/// history is not lost.
+fn resolve_session_meta(
+    mut metas_a: Vec<SessionMetaWithGit>,
+    mut metas_b: Vec<SessionMetaWithGit>,
+) -> Vec<RolloutItem> {
+    let winner_a = metas_a.pop();
+    let winner_b = metas_b.pop();
+
+    let (winner, mut losers) = match (winner_a, winner_b) {
+        (Some(a), Some(b)) => {
+            if a.timestamp() >= b.timestamp() {
+                (Some(a), vec![b])
+            } else {
+                (Some(b), vec![a])
+            }
+        }
+        (Some(a), None) => (Some(a), Vec::new()),
+        (None, Some(b)) => (Some(b), Vec::new()),
+        (None, None) => (None, Vec::new()),
+    };
+    losers.extend(metas_a);
+    losers.extend(metas_b);
+
+    let mut items = Vec::with_capacity(1 + losers.len());
+    items.extend(winner.map(RolloutItem::SessionMeta));
+    items.extend(losers.into_iter().map(RolloutItem::SessionMeta));
+    items
+}
+
+fn items_equal(a: &RolloutItem, b: &RolloutItem) -> bool {
+    to_value(a) == to_value(b)
+}
+
+fn to_value(item: &RolloutItem) -> Value {
+    match item {
+        RolloutItem::ResponseItem(item) => serde_json::to_value(item).unwrap_or(Value::Null),
+        RolloutItem::Event(event) => serde_json::to_value(event).unwrap_or(Value::Null),
+        RolloutItem::SessionMeta(meta) => serde_json::to_value(meta).unwrap_or(Value::Null),
+    }
+}
+
+fn identity_for(item: &RolloutItem) -> RecordIdentity {
+    match item {
+        RolloutItem::ResponseItem(item) => response_item_identity(item)
+            .map(RecordIdentity::ResponseItem)
+            .unwrap_or_else(|| RecordIdentity::Content(content_hash(item))),
+        RolloutItem::Event(event) => RecordIdentity::Event(event.id.clone()),
+        RolloutItem::SessionMeta(_) => RecordIdentity::Content(content_hash(item)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codex_protocol::models::ContentItem;
+    use codex_protocol::models::FunctionCallOutputPayload;
+
+    use super::*;
+
+    fn user_message(text: &str) -> RolloutItem {
+        RolloutItem::ResponseItem(ResponseItem::Message {
+            id: None,
+            role: "user".to_string(),
+            content: vec![ContentItem::InputText {
+                text: text.to_string(),
+            }],
+        })
+    }
+
+    #[test]
+    fn identical_prefix_is_treated_as_shared_history() {
+        let shared = vec![user_message("hello")];
+        let mut body_a = shared.clone();
+        body_a.push(user_message("from a"));
+        let mut body_b = shared;
+        body_b.push(user_message("from b"));
+
+        let (merged, conflicts) = reconcile_bodies(&body_a, &body_b);
+
+        // The shared "hello" appears once, followed by both distinct tails.
+        assert_eq!(merged.len(), 3);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn distinct_idless_messages_at_the_same_tail_offset_are_not_collapsed() {
+        // Two unrelated plain messages landing at the same post-divergence
+        // offset in each file must both survive the merge rather than being
+        // treated as the same record (and one silently overwriting the
+        // other) just because neither carries an `id`.
+        let body_a = vec![user_message("what's the weather today?")];
+        let body_b = vec![user_message("can you review this diff?")];
+
+        let (merged, conflicts) = reconcile_bodies(&body_a, &body_b);
+
+        assert_eq!(merged.len(), 2, "both distinct messages should survive");
+        assert!(
+            conflicts.is_empty(),
+            "content-hash identity must not treat these as the same record"
+        );
+    }
+
+    #[test]
+    fn identical_idless_messages_at_the_same_tail_offset_collapse_once() {
+        let body_a = vec![user_message("same text")];
+        let body_b = vec![user_message("same text")];
+
+        let (merged, conflicts) = reconcile_bodies(&body_a, &body_b);
+
+        assert_eq!(merged.len(), 1);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn conflicting_identity_reports_a_conflict_and_keeps_latest() {
+        let call_a = RolloutItem::ResponseItem(ResponseItem::FunctionCallOutput {
+            call_id: "call-1".to_string(),
+            output: FunctionCallOutputPayload {
+                content: "result a".to_string(),
+                success: Some(true),
+            },
+        });
+        let call_b = RolloutItem::ResponseItem(ResponseItem::FunctionCallOutput {
+            call_id: "call-1".to_string(),
+            output: FunctionCallOutputPayload {
+                content: "result b".to_string(),
+                success: Some(true),
+            },
+        });
+
+        let (merged, conflicts) = reconcile_bodies(&[call_a], &[call_b]);
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(conflicts.len(), 1);
+        // Last-writer-wins: the second file's value is kept.
+        match &merged[0] {
+            RolloutItem::ResponseItem(ResponseItem::FunctionCallOutput { output, .. }) => {
+                assert_eq!(output.content, "result b");
+            }
+            other => panic!("expected the function call output, got {other:?}"),
+        }
+    }
+}
+
+/// Identity for a body record that carries no stable id of its own (e.g. a
+/// plain `ResponseItem::Message`, `id: None`). Hashing the serialized body
+/// means two *distinct* id-less records collide only if their contents are
+/// byte-for-byte identical, rather than whenever they land at the same
+/// offset from the divergence point.
+fn content_hash(item: &RolloutItem) -> u64 {
+    use std::hash::Hash;
+    use std::hash::Hasher;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    to_value(item).to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The id is prefixed with the record's variant so that a call and its
+/// output, which share a `call_id`, are keyed as two distinct records.
+fn response_item_identity(item: &ResponseItem) -> Option<String> {
+    match item {
+        ResponseItem::Message { id, .. } => id.as_ref().map(|id| format!("message:{id}")),
+        ResponseItem::Reasoning { id, .. } => Some(format!("reasoning:{id}")),
+        ResponseItem::LocalShellCall { call_id, id, .. } => call_id
+            .clone()
+            .or_else(|| id.clone())
+            .map(|id| format!("local_shell_call:{id}")),
+        ResponseItem::FunctionCall { call_id, .. } => Some(format!("function_call:{call_id}")),
+        ResponseItem::FunctionCallOutput { call_id, .. } => {
+            Some(format!("function_call_output:{call_id}"))
+        }
+        ResponseItem::CustomToolCall { call_id, .. } => Some(format!("custom_tool_call:{call_id}")),
+        ResponseItem::CustomToolCallOutput { call_id, .. } => {
+            Some(format!("custom_tool_call_output:{call_id}"))
+        }
+        _ => None,
+    }
+}
@@ -22,10 +22,15 @@ use tracing::warn;
 use uuid::Uuid;
 
 use super::SESSIONS_SUBDIR;
+use super::index::RolloutStats;
+use super::index::SidecarIndex;
+use super::index::read_index;
+use super::index::write_index;
 use super::list::ConversationsPage;
 use super::list::Cursor;
 use super::list::get_conversations;
 use super::policy::is_persisted_response_item;
+use super::transform::RolloutTransformChain;
 use crate::config::Config;
 use crate::conversation_manager::InitialHistory;
 use crate::git_info::GitInfo;
@@ -51,6 +56,12 @@ pub struct SessionMetaWithGit {
     git: Option<GitInfo>,
 }
 
+impl SessionMetaWithGit {
+    pub(crate) fn timestamp(&self) -> &str {
+        &self.meta.timestamp
+    }
+}
+
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub struct SessionStateSnapshot {}
 
@@ -105,7 +116,24 @@ impl From<Event> for RolloutItem {
     }
 }
 
-enum RolloutCmd {
+/// How [`RolloutRecorder::fork`] should treat the source session's metadata
+/// when branching it into a new rollout file.
+#[derive(Debug, Clone)]
+pub enum ForkMetaDirective {
+    /// Preserve the source `SessionMetaWithGit` verbatim, recording it as a
+    /// `prev_session_meta` line ahead of a fresh `session_meta` with a new
+    /// id/timestamp.
+    Copy,
+    /// Start the forked session with a new `cwd`/`instructions` and
+    /// re-collected git info, keeping the recorded item stream intact but
+    /// dropping the source's meta lineage.
+    Replace {
+        cwd: PathBuf,
+        instructions: Option<String>,
+    },
+}
+
+pub(crate) enum RolloutCmd {
     AddResponseItems(Vec<ResponseItem>),
     AddEvents(Vec<Event>),
     AddSessionMeta(SessionMetaWithGit),
@@ -157,6 +185,9 @@ impl RolloutRecorder {
         // Spawn a Tokio task that owns the file handle and performs async
         // writes. Using `tokio::fs::File` keeps everything on the async I/O
         // driver instead of blocking the runtime.
+        let transforms = super::transform::load_transform_chain(&config.rollout_transforms).await?;
+        let batch = BatchConfig::from_config(config);
+
         tokio::task::spawn(rollout_writer(
             tokio::fs::File::from_std(file),
             rx,
@@ -168,7 +199,12 @@ impl RolloutRecorder {
                 cli_version: env!("CARGO_PKG_VERSION").to_string(),
                 instructions,
             }),
+            None,
             cwd,
+            transforms,
+            batch,
+            path.clone(),
+            None,
         ));
 
         Ok(Self { tx, path })
@@ -274,10 +310,154 @@ impl RolloutRecorder {
         }
     }
 
+    /// Branches `source_path` into a brand-new rollout file: every
+    /// persisted [`ResponseItem`] and [`Event`] is copied across, and
+    /// `directive` controls what happens to the source's `SessionMeta`
+    /// (see [`ForkMetaDirective`]). Unlike resuming, the original file is
+    /// left untouched and the returned recorder is a live session that can
+    /// keep appending to the fork.
+    pub async fn fork(
+        config: &Config,
+        source_path: &Path,
+        directive: ForkMetaDirective,
+    ) -> std::io::Result<Self> {
+        let items = match Self::get_rollout_history(source_path).await? {
+            InitialHistory::Resumed(items) => items,
+            InitialHistory::New => Vec::new(),
+        };
+        let is_replace = matches!(directive, ForkMetaDirective::Replace { .. });
+
+        let source_meta = items.iter().find_map(|item| match item {
+            RolloutItem::SessionMeta(meta) => Some(meta.clone()),
+            _ => None,
+        });
+        // `ForkMetaDirective::Copy` writes the source meta as a
+        // `prev_session_meta` line directly (see the `rollout_writer` call
+        // below), ahead of the fresh `session_meta`, so it must not also be
+        // re-sent through the command stream below.
+        let prev_meta_for_writer = if is_replace { None } else { source_meta.clone() };
+
+        let new_uuid = Uuid::new_v4();
+        let LogFileInfo {
+            file,
+            session_id,
+            timestamp,
+            path,
+        } = create_log_file(config, new_uuid)?;
+
+        let timestamp_format: &[FormatItem] = format_description!(
+            "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:3]Z"
+        );
+        let timestamp = timestamp
+            .to_offset(time::UtcOffset::UTC)
+            .format(timestamp_format)
+            .map_err(|e| IoError::other(format!("failed to format timestamp: {e}")))?;
+
+        let (fresh_meta, cwd) = match (directive, source_meta.as_ref()) {
+            (ForkMetaDirective::Replace { cwd, instructions }, _) => {
+                let meta = SessionMeta {
+                    id: session_id,
+                    timestamp: timestamp.clone(),
+                    cwd: cwd.to_string_lossy().to_string(),
+                    originator: config.responses_originator_header.clone(),
+                    cli_version: env!("CARGO_PKG_VERSION").to_string(),
+                    instructions,
+                };
+                (meta, cwd)
+            }
+            (ForkMetaDirective::Copy, Some(prev)) => {
+                let meta = SessionMeta {
+                    id: session_id,
+                    timestamp: timestamp.clone(),
+                    ..prev.meta.clone()
+                };
+                let cwd = PathBuf::from(&prev.meta.cwd);
+                (meta, cwd)
+            }
+            (ForkMetaDirective::Copy, None) => {
+                let meta = SessionMeta {
+                    id: session_id,
+                    timestamp: timestamp.clone(),
+                    cwd: config.cwd.to_string_lossy().to_string(),
+                    originator: config.responses_originator_header.clone(),
+                    cli_version: env!("CARGO_PKG_VERSION").to_string(),
+                    instructions: None,
+                };
+                (meta, config.cwd.clone())
+            }
+        };
+
+        let (tx, rx) = mpsc::channel::<RolloutCmd>(256);
+        let transforms = super::transform::load_transform_chain(&config.rollout_transforms).await?;
+        let batch = BatchConfig::from_config(config);
+
+        tokio::task::spawn(rollout_writer(
+            tokio::fs::File::from_std(file),
+            rx,
+            Some(fresh_meta),
+            prev_meta_for_writer,
+            cwd,
+            transforms,
+            batch,
+            path.clone(),
+            None,
+        ));
+
+        let recorder = Self { tx, path };
+
+        for item in items {
+            // The source session's meta is already written directly by
+            // `rollout_writer` above (as `prev_session_meta` for `Copy`, or
+            // dropped entirely for `Replace`); re-sending it here would
+            // duplicate or resurrect it.
+            if matches!(item, RolloutItem::SessionMeta(_)) {
+                continue;
+            }
+            recorder.record_items(item).await?;
+        }
+
+        Ok(recorder)
+    }
+
     pub fn path(&self) -> &Path {
         &self.path
     }
 
+    /// Returns counts of response items, tool calls, and events plus total
+    /// bytes for the rollout at `path`, read from its sidecar index when
+    /// present so callers (e.g. TUIs) don't need to parse the whole
+    /// `.jsonl` just to show a summary.
+    pub async fn stats(path: &Path) -> std::io::Result<RolloutStats> {
+        if let Some(index) = read_index(path).await? {
+            if !super::index::index_is_stale(path, &index).await? {
+                return Ok(RolloutStats::from(&index));
+            }
+        }
+
+        // Sidecar missing or stale (e.g. written by an older Codex version,
+        // or a write landed after the index was last saved): fall back to a
+        // full parse. A sidecar that exists but cannot be read or parsed is
+        // returned as an error above rather than falling back.
+        let mut stats = RolloutStats::default();
+        if let InitialHistory::Resumed(items) = Self::get_rollout_history(path).await? {
+            for item in items {
+                match item {
+                    RolloutItem::ResponseItem(item) => {
+                        if is_tool_call(&item) {
+                            stats.tool_calls += 1;
+                        } else {
+                            stats.response_items += 1;
+                        }
+                    }
+                    RolloutItem::Event(_) => stats.events += 1,
+                    RolloutItem::SessionMeta(_) => {}
+                }
+            }
+        }
+        stats.total_bytes = tokio::fs::metadata(path).await?.len();
+        Ok(stats)
+    }
+
     pub async fn shutdown(&self) -> std::io::Result<()> {
         let (tx_done, rx_done) = oneshot::channel();
         match self.tx.send(RolloutCmd::Shutdown { ack: tx_done }).await {
@@ -294,6 +474,18 @@ impl RolloutRecorder {
     }
 }
 
+/// Whether a response item represents a tool/function call rather than a
+/// plain message, used to split [`RolloutStats::tool_calls`] out from
+/// [`RolloutStats::response_items`].
+fn is_tool_call(item: &ResponseItem) -> bool {
+    matches!(
+        item,
+        ResponseItem::FunctionCall { .. }
+            | ResponseItem::LocalShellCall { .. }
+            | ResponseItem::CustomToolCall { .. }
+    )
+}
+
 struct LogFileInfo {
     /// Opened file handle to the rollout file.
     file: File,
@@ -343,13 +535,116 @@ fn create_log_file(config: &Config, session_id: Uuid) -> std::io::Result<LogFile
     })
 }
 
-async fn rollout_writer(
+/// Batching/throttling thresholds for [`JsonlWriter`], read from `Config` so
+/// deployments can tune write amplification vs. durability latency.
+///
+/// `Config` (in `codex-rs/core/src/config.rs`, not part of this module) is
+/// expected to carry `pub rollout_batch_max_bytes: usize`,
+/// `pub rollout_batch_min_latency_ms: u64`, and
+/// `pub rollout_batch_max_latency_ms: u64`, consumed by
+/// [`BatchConfig::from_config`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BatchConfig {
+    /// Flush once the buffer reaches this many bytes, regardless of how long
+    /// it has been since the last flush.
+    max_bytes: usize,
+    /// Starting and smallest flush deadline: the buffer is flushed once the
+    /// tranquilizer's current window (never shorter than this) has elapsed
+    /// since it went from empty to non-empty, even if `max_bytes` was never
+    /// reached.
+    min_latency: std::time::Duration,
+    /// Upper bound the tranquilizer may widen the flush deadline to when the
+    /// disk is observed to be slow.
+    max_latency: std::time::Duration,
+}
+
+impl BatchConfig {
+    /// Builds a `BatchConfig` directly from thresholds rather than `Config`,
+    /// for callers (e.g. the replay benchmark harness) that drive
+    /// `rollout_writer` outside of a full `RolloutRecorder::new`/`fork`.
+    pub(crate) fn new(
+        max_bytes: usize,
+        min_latency: std::time::Duration,
+        max_latency: std::time::Duration,
+    ) -> Self {
+        Self {
+            max_bytes,
+            min_latency,
+            max_latency,
+        }
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            max_bytes: config.rollout_batch_max_bytes,
+            min_latency: std::time::Duration::from_millis(config.rollout_batch_min_latency_ms),
+            max_latency: std::time::Duration::from_millis(config.rollout_batch_max_latency_ms),
+        }
+    }
+}
+
+/// Widens or narrows the flush deadline based on how long recent flushes
+/// took, so a slow disk amortizes more writes per fsync while an idle/fast
+/// disk stays close to synchronous.
+struct Tranquilizer {
+    min_latency: std::time::Duration,
+    max_latency: std::time::Duration,
+    window: std::time::Duration,
+}
+
+impl Tranquilizer {
+    fn new(min_latency: std::time::Duration, max_latency: std::time::Duration) -> Self {
+        Self {
+            min_latency,
+            max_latency,
+            window: min_latency,
+        }
+    }
+
+    /// Records how long a flush took and adjusts the next window: a flush
+    /// that took a meaningful fraction of the current window suggests the
+    /// disk is the bottleneck, so we widen it; a fast flush narrows it back
+    /// towards `min_latency`.
+    fn observe(&mut self, flush_duration: std::time::Duration) {
+        if flush_duration * 2 > self.window {
+            self.window = (self.window * 2).min(self.max_latency);
+        } else {
+            self.window = (self.window / 2).max(self.min_latency);
+        }
+    }
+
+    fn window(&self) -> std::time::Duration {
+        self.window
+    }
+}
+
+pub(crate) async fn rollout_writer(
     file: tokio::fs::File,
     mut rx: mpsc::Receiver<RolloutCmd>,
     mut meta: Option<SessionMeta>,
+    prev_meta: Option<SessionMetaWithGit>,
     cwd: std::path::PathBuf,
+    transforms: RolloutTransformChain,
+    batch: BatchConfig,
+    path: PathBuf,
+    flush_observer: Option<mpsc::UnboundedSender<std::time::Duration>>,
 ) -> std::io::Result<()> {
-    let mut writer = JsonlWriter { file };
+    let mut writer = JsonlWriter::new(file);
+    let mut tranquilizer = Tranquilizer::new(batch.min_latency, batch.max_latency);
+    let mut index = SidecarIndex::default();
+
+    // A forked session's source meta is written first, as `prev_session_meta`,
+    // so it precedes the fresh `session_meta` below rather than trailing it.
+    if let Some(prev_session_meta) = prev_meta {
+        write_transformed(
+            &mut writer,
+            &transforms,
+            RolloutItem::SessionMeta(prev_session_meta),
+            "prev_session_meta",
+            &mut index,
+        )
+        .await?;
+    }
 
     // If we have a meta, collect git info asynchronously and write meta first
     if let Some(session_meta) = meta.take() {
@@ -358,68 +653,428 @@ async fn rollout_writer(
             meta: session_meta,
             git: git_info,
         };
-        // Write the SessionMeta as the first item in the file
-        writer
-            .write_line(&SessionMetaLine {
-                record_type: "session_meta",
-                meta: &session_meta_with_git,
-            })
-            .await?;
+        index.session_meta = Some(session_meta_with_git.clone());
+        write_transformed(
+            &mut writer,
+            &transforms,
+            RolloutItem::SessionMeta(session_meta_with_git),
+            "session_meta",
+            &mut index,
+        )
+        .await?;
     }
 
-    // Process rollout commands
-    while let Some(cmd) = rx.recv().await {
-        match cmd {
-            RolloutCmd::AddResponseItems(items) => {
-                for item in items {
-                    if is_persisted_response_item(&item) {
-                        writer.write_line(&item).await?;
-                    }
-                }
+    if writer.len() > 0 {
+        flush(
+            &mut writer,
+            &mut tranquilizer,
+            &path,
+            &index,
+            flush_observer.as_ref(),
+        )
+        .await?;
+    }
+
+    // Set when the buffer transitions from empty to non-empty and cleared
+    // on every flush, so the max-latency deadline below is anchored to the
+    // oldest unflushed record rather than restarted on every loop trip —
+    // otherwise continuous sub-`window` traffic could starve it forever.
+    let mut deadline_at: Option<std::time::Instant> = None;
+
+    'outer: loop {
+        let sleep_for = match deadline_at {
+            Some(started_at) => tranquilizer.window().saturating_sub(started_at.elapsed()),
+            // Nothing buffered: no deadline to race against, just wait on
+            // the channel. A long sleep rather than `Duration::MAX` keeps
+            // `tokio::time::sleep` from overflowing its internal timer wheel.
+            None => std::time::Duration::from_secs(3600),
+        };
+        let deadline = tokio::time::sleep(sleep_for);
+        tokio::pin!(deadline);
+
+        let cmd = tokio::select! {
+            biased;
+            cmd = rx.recv() => cmd,
+            () = &mut deadline => {
+                flush(
+                    &mut writer,
+                    &mut tranquilizer,
+                    &path,
+                    &index,
+                    flush_observer.as_ref(),
+                )
+                .await?;
+                deadline_at = None;
+                continue 'outer;
             }
-            RolloutCmd::AddEvents(events) => {
-                for event in events {
-                    #[derive(Serialize)]
-                    struct EventLine<'a> {
-                        record_type: &'static str,
-                        #[serde(flatten)]
-                        event: &'a Event,
+        };
+        let Some(cmd) = cmd else { break 'outer };
+        let mut shutdown_ack = apply_cmd(&mut writer, &transforms, cmd, &mut index).await?;
+        if deadline_at.is_none() && writer.len() > 0 {
+            deadline_at = Some(std::time::Instant::now());
+        }
+
+        // Greedily drain whatever else is already queued so a burst of
+        // sends amortizes into one flush instead of one fsync each.
+        while shutdown_ack.is_none() {
+            match rx.try_recv() {
+                Ok(cmd) => {
+                    shutdown_ack = apply_cmd(&mut writer, &transforms, cmd, &mut index).await?;
+                    if deadline_at.is_none() && writer.len() > 0 {
+                        deadline_at = Some(std::time::Instant::now());
                     }
-                    writer
-                        .write_line(&EventLine {
-                            record_type: "event",
-                            event: &event,
-                        })
-                        .await?;
                 }
+                Err(_) => break,
             }
-            RolloutCmd::AddSessionMeta(meta) => {
-                writer
-                    .write_line(&SessionMetaLine {
-                        record_type: "prev_session_meta",
-                        meta: &meta,
-                    })
+            if writer.len() >= batch.max_bytes {
+                break;
+            }
+        }
+
+        if writer.len() >= batch.max_bytes || shutdown_ack.is_some() {
+            flush(
+                &mut writer,
+                &mut tranquilizer,
+                &path,
+                &index,
+                flush_observer.as_ref(),
+            )
+            .await?;
+            deadline_at = None;
+        }
+
+        if let Some(ack) = shutdown_ack {
+            let _ = ack.send(());
+            break 'outer;
+        }
+    }
+
+    // Final flush so `shutdown()` only returns once everything is durable,
+    // even if we broke out of the loop via the channel closing rather than
+    // an explicit `Shutdown` command.
+    flush(
+        &mut writer,
+        &mut tranquilizer,
+        &path,
+        &index,
+        flush_observer.as_ref(),
+    )
+    .await
+}
+
+/// Applies one [`RolloutCmd`] to the buffered writer. Returns the shutdown
+/// ack sender once a `Shutdown` has been handled, so the caller can flush
+/// before acknowledging it.
+async fn apply_cmd(
+    writer: &mut JsonlWriter,
+    transforms: &RolloutTransformChain,
+    cmd: RolloutCmd,
+    index: &mut SidecarIndex,
+) -> std::io::Result<Option<oneshot::Sender<()>>> {
+    match cmd {
+        RolloutCmd::AddResponseItems(items) => {
+            for item in items {
+                if is_persisted_response_item(&item) {
+                    write_transformed(
+                        writer,
+                        transforms,
+                        RolloutItem::ResponseItem(item),
+                        "response",
+                        index,
+                    )
                     .await?;
+                }
             }
-            RolloutCmd::Shutdown { ack } => {
-                let _ = ack.send(());
+            Ok(None)
+        }
+        RolloutCmd::AddEvents(events) => {
+            for event in events {
+                if is_persisted_event(&event) {
+                    write_transformed(writer, transforms, RolloutItem::Event(event), "event", index)
+                        .await?;
+                }
             }
+            Ok(None)
+        }
+        RolloutCmd::AddSessionMeta(meta) => {
+            write_transformed(
+                writer,
+                transforms,
+                RolloutItem::SessionMeta(meta),
+                "prev_session_meta",
+                index,
+            )
+            .await?;
+            Ok(None)
         }
+        RolloutCmd::Shutdown { ack } => Ok(Some(ack)),
     }
+}
+
+/// Flushes the buffered writer and, if anything was written, persists the
+/// sidecar index alongside it. `flush_observer`, when set, is sent the
+/// observed flush duration so callers outside this module (e.g. the replay
+/// benchmark harness) can measure real batch flushes instead of duplicating
+/// this loop's batching logic; a dropped receiver is not an error.
+async fn flush(
+    writer: &mut JsonlWriter,
+    tranquilizer: &mut Tranquilizer,
+    path: &Path,
+    index: &SidecarIndex,
+    flush_observer: Option<&mpsc::UnboundedSender<std::time::Duration>>,
+) -> std::io::Result<()> {
+    if let Some(duration) = writer.flush().await? {
+        tranquilizer.observe(duration);
+        write_index(path, index).await?;
+        if let Some(observer) = flush_observer {
+            let _ = observer.send(duration);
+        }
+    }
+    Ok(())
+}
+
+/// Runs `item` through the configured transform chain and, unless a
+/// transform dropped it, serializes it with `record_type` the way the rest
+/// of this module expects (session meta lines are flattened, response items
+/// are written bare), recording it in the sidecar `index` along the way.
+async fn write_transformed(
+    writer: &mut JsonlWriter,
+    transforms: &RolloutTransformChain,
+    item: RolloutItem,
+    record_type: &'static str,
+    index: &mut SidecarIndex,
+) -> std::io::Result<()> {
+    let Some(item) = transforms.apply(item).await? else {
+        return Ok(());
+    };
+
+    let offset = writer.pending_offset();
+    let index_label = match &item {
+        RolloutItem::ResponseItem(item) if is_tool_call(item) => "tool_call",
+        RolloutItem::ResponseItem(_) => "response",
+        _ => record_type,
+    };
+    let timestamp = match &item {
+        RolloutItem::SessionMeta(meta) => Some(meta.timestamp().to_string()),
+        _ => None,
+    };
 
+    match item {
+        RolloutItem::ResponseItem(item) => writer.write_line(&item).await?,
+        RolloutItem::Event(event) => {
+            #[derive(Serialize)]
+            struct EventLine<'a> {
+                record_type: &'static str,
+                #[serde(flatten)]
+                event: &'a Event,
+            }
+            writer
+                .write_line(&EventLine {
+                    record_type,
+                    event: &event,
+                })
+                .await?
+        }
+        RolloutItem::SessionMeta(meta) => {
+            writer
+                .write_line(&SessionMetaLine {
+                    record_type,
+                    meta: &meta,
+                })
+                .await?
+        }
+    }
+
+    let written = writer.pending_offset() - offset;
+    index.record(index_label, offset, written, timestamp.as_deref());
     Ok(())
 }
 
-struct JsonlWriter {
+/// Buffers serialized lines in memory and only touches the file on an
+/// explicit [`flush`](Self::flush), so callers can coalesce many
+/// `write_line`s into one `write_all` + `fsync`.
+pub(crate) struct JsonlWriter {
     file: tokio::fs::File,
+    buffer: Vec<u8>,
+    /// Bytes already durably flushed to `file`, used to compute the byte
+    /// offset of records still sitting in `buffer` for [`SidecarIndex`].
+    total_flushed: u64,
 }
 
 impl JsonlWriter {
-    async fn write_line(&mut self, item: &impl serde::Serialize) -> std::io::Result<()> {
-        let mut json = serde_json::to_string(item)?;
-        json.push('\n');
-        let _ = self.file.write_all(json.as_bytes()).await;
-        self.file.flush().await?;
+    pub(crate) fn new(file: tokio::fs::File) -> Self {
+        Self {
+            file,
+            buffer: Vec::new(),
+            total_flushed: 0,
+        }
+    }
+
+    /// Appends one record to the in-memory buffer. Does not touch the file;
+    /// call [`flush`](Self::flush) to make it durable.
+    pub(crate) async fn write_line(&mut self, item: &impl serde::Serialize) -> std::io::Result<()> {
+        serde_json::to_writer(&mut self.buffer, item)?;
+        self.buffer.push(b'\n');
         Ok(())
     }
+
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Byte offset, within the rollout file as a whole, of the next byte
+    /// that would be appended to `buffer`.
+    fn pending_offset(&self) -> u64 {
+        self.total_flushed + self.buffer.len() as u64
+    }
+
+    /// Bytes already durably flushed to disk.
+    pub(crate) fn total_flushed(&self) -> u64 {
+        self.total_flushed
+    }
+
+    /// Writes and fsyncs the buffered bytes, returning how long the flush
+    /// took (`None` if there was nothing to flush).
+    pub(crate) async fn flush(&mut self) -> std::io::Result<Option<std::time::Duration>> {
+        if self.buffer.is_empty() {
+            return Ok(None);
+        }
+        let start = std::time::Instant::now();
+        self.file.write_all(&self.buffer).await?;
+        self.file.flush().await?;
+        self.file.sync_data().await?;
+        self.total_flushed += self.buffer.len() as u64;
+        self.buffer.clear();
+        Ok(Some(start.elapsed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn tranquilizer_widens_window_when_flush_takes_a_meaningful_fraction_of_it() {
+        let min = Duration::from_millis(10);
+        let max = Duration::from_millis(1000);
+        let mut tranquilizer = Tranquilizer::new(min, max);
+        assert_eq!(tranquilizer.window(), min);
+
+        // A flush taking more than half the current (10ms) window should
+        // widen it rather than leave the disk starved for batching room.
+        tranquilizer.observe(Duration::from_millis(8));
+
+        assert_eq!(tranquilizer.window(), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn tranquilizer_narrows_window_back_towards_min_on_fast_flushes() {
+        let min = Duration::from_millis(10);
+        let max = Duration::from_millis(1000);
+        let mut tranquilizer = Tranquilizer::new(min, max);
+        tranquilizer.observe(Duration::from_millis(8)); // widen to 20ms
+        tranquilizer.observe(Duration::from_millis(8)); // widen to 40ms
+
+        tranquilizer.observe(Duration::from_millis(1)); // fast: narrow
+
+        assert_eq!(tranquilizer.window(), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn tranquilizer_never_widens_past_max_latency() {
+        let min = Duration::from_millis(10);
+        let max = Duration::from_millis(50);
+        let mut tranquilizer = Tranquilizer::new(min, max);
+
+        for _ in 0..10 {
+            tranquilizer.observe(Duration::from_millis(1000));
+        }
+
+        assert_eq!(tranquilizer.window(), max);
+    }
+
+    #[test]
+    fn tranquilizer_never_narrows_past_min_latency() {
+        let min = Duration::from_millis(10);
+        let max = Duration::from_millis(1000);
+        let mut tranquilizer = Tranquilizer::new(min, max);
+
+        for _ in 0..10 {
+            tranquilizer.observe(Duration::ZERO);
+        }
+
+        assert_eq!(tranquilizer.window(), min);
+    }
+
+    #[tokio::test]
+    async fn forked_source_meta_is_written_as_prev_session_meta_ahead_of_the_fresh_meta() {
+        let dir = tempfile_dir();
+        let path = dir.join("rollout-test.jsonl");
+        let file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&path)
+            .await
+            .unwrap();
+
+        let prev_meta = SessionMetaWithGit {
+            meta: SessionMeta {
+                id: Uuid::nil(),
+                timestamp: "2024-01-01T00:00:00.000Z".to_string(),
+                cwd: "/tmp".to_string(),
+                originator: "test".to_string(),
+                cli_version: "0.0.0".to_string(),
+                instructions: None,
+            },
+            git: None,
+        };
+        let fresh_meta = SessionMeta {
+            id: Uuid::nil(),
+            timestamp: "2024-01-02T00:00:00.000Z".to_string(),
+            cwd: "/tmp".to_string(),
+            originator: "test".to_string(),
+            cli_version: "0.0.0".to_string(),
+            instructions: None,
+        };
+
+        let batch = BatchConfig::new(usize::MAX, Duration::from_secs(3600), Duration::from_secs(3600));
+        let (cmd_tx, cmd_rx) = mpsc::channel::<RolloutCmd>(1);
+        let writer_task = tokio::task::spawn(rollout_writer(
+            file,
+            cmd_rx,
+            Some(fresh_meta),
+            Some(prev_meta),
+            PathBuf::from("/tmp"),
+            RolloutTransformChain::default(),
+            batch,
+            path.clone(),
+            None,
+        ));
+
+        let (ack_tx, ack_rx) = oneshot::channel();
+        cmd_tx
+            .send(RolloutCmd::Shutdown { ack: ack_tx })
+            .await
+            .unwrap();
+        ack_rx.await.unwrap();
+        writer_task.await.unwrap().unwrap();
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let first: Value = serde_json::from_str(lines[0]).unwrap();
+        let second: Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(first["record_type"], "prev_session_meta");
+        assert_eq!(second["record_type"], "session_meta");
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    fn tempfile_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("codex-rollout-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
 }
@@ -0,0 +1,292 @@
+//! Pluggable transform pipeline for rollout records.
+//!
+//! Each transform is a sandboxed WASM component (no network access) that is
+//! shown every [`RolloutItem`] before it reaches [`JsonlWriter`](super::recorder)
+//! and may rewrite, drop, or keep it. Transforms are declared in [`Config`](crate::config::Config)
+//! and chained in the order they are configured, so a redaction module can
+//! run ahead of, say, a PII scrubber.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::recorder::RolloutItem;
+
+/// The subset of rollout record kinds a transform wants to observe. A
+/// transform that only lists `Event` is never shown response items, which
+/// keeps the sandbox call count down for modules that only care about one
+/// record type.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RolloutRecordKind {
+    Response,
+    Event,
+    SessionMeta,
+}
+
+impl RolloutRecordKind {
+    fn of(item: &RolloutItem) -> Self {
+        match item {
+            RolloutItem::ResponseItem(_) => RolloutRecordKind::Response,
+            RolloutItem::Event(_) => RolloutRecordKind::Event,
+            RolloutItem::SessionMeta(_) => RolloutRecordKind::SessionMeta,
+        }
+    }
+}
+
+/// Manifest shipped alongside a transform's `.wasm` component, e.g.
+/// `redact-secrets.manifest.json` next to `redact-secrets.wasm`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TransformManifest {
+    /// Semver of the transform module. Only used for diagnostics; Codex does
+    /// not enforce compatibility ranges.
+    pub version: String,
+    /// Record kinds this module wants to see.
+    pub record_types: Vec<RolloutRecordKind>,
+    /// Optional JSON schema describing the module's `config`. `None` means
+    /// the module takes no configuration. The host does not validate
+    /// `config` against this schema.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_schema: Option<Value>,
+}
+
+/// What a transform wants done with a record it was shown.
+#[derive(Debug, Clone)]
+pub enum TransformOutcome {
+    /// Leave the record as-is.
+    Keep,
+    /// Replace the record with a new one before the next transform runs.
+    Replace(RolloutItem),
+    /// Discard the record: it is never written and no later transform sees it.
+    Drop,
+}
+
+/// A single step in the rollout transform pipeline.
+///
+/// Implementors are expected to run inside a sandbox (see
+/// [`WasmRolloutTransform`]) with no network access; the host only ever
+/// exchanges a serialized record and the module's JSON config with them.
+#[async_trait::async_trait]
+pub trait RolloutTransform: Send + Sync {
+    fn manifest(&self) -> &TransformManifest;
+
+    async fn transform(&self, item: &RolloutItem) -> std::io::Result<TransformOutcome>;
+}
+
+/// A transform backed by a WASM component compiled once from
+/// `component_path` when the chain is loaded.
+///
+/// Instantiation grants no network access and preopens no directories, and
+/// the host calls only a `transform(record_json, config_json) -> TransformResult`
+/// export; the guest still has the baseline WASI clocks, random and stdio.
+pub struct WasmRolloutTransform {
+    manifest: TransformManifest,
+    config: Value,
+    compiled: super::wasm_host::CompiledTransform,
+}
+
+impl WasmRolloutTransform {
+    /// Compiles `component_path`, which is CPU-bound, so this is done once
+    /// at chain-load time rather than on every record.
+    pub async fn new(
+        component_path: PathBuf,
+        manifest: TransformManifest,
+        config: Value,
+    ) -> std::io::Result<Self> {
+        let compiled = super::wasm_host::compile_transform(component_path).await?;
+        Ok(Self {
+            manifest,
+            config,
+            compiled,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl RolloutTransform for WasmRolloutTransform {
+    fn manifest(&self) -> &TransformManifest {
+        &self.manifest
+    }
+
+    async fn transform(&self, item: &RolloutItem) -> std::io::Result<TransformOutcome> {
+        super::wasm_host::run_transform(&self.compiled, &self.config, item).await
+    }
+}
+
+/// Ordered chain of transforms applied to every record before it is written.
+///
+/// Transforms run in configuration order; a `Drop` or `Replace` is threaded
+/// into the next transform's input, so later modules in the chain always see
+/// the output of earlier ones.
+#[derive(Clone, Default)]
+pub struct RolloutTransformChain {
+    transforms: Arc<Vec<Box<dyn RolloutTransform>>>,
+}
+
+impl RolloutTransformChain {
+    pub fn new(transforms: Vec<Box<dyn RolloutTransform>>) -> Self {
+        Self {
+            transforms: Arc::new(transforms),
+        }
+    }
+
+    /// Runs `item` through every transform that declared interest in its
+    /// [`RolloutRecordKind`]. Returns `None` if any transform dropped it.
+    pub async fn apply(&self, item: RolloutItem) -> std::io::Result<Option<RolloutItem>> {
+        let mut current = item;
+        for transform in self.transforms.iter() {
+            let kind = RolloutRecordKind::of(&current);
+            if !transform.manifest().record_types.contains(&kind) {
+                continue;
+            }
+            match transform.transform(&current).await? {
+                TransformOutcome::Keep => {}
+                TransformOutcome::Replace(replacement) => current = replacement,
+                TransformOutcome::Drop => return Ok(None),
+            }
+        }
+        Ok(Some(current))
+    }
+}
+
+/// One entry of `Config`'s `rollout_transforms` list: which WASM component
+/// to load and the config to hand it. The module's [`TransformManifest`] is
+/// read from `<component_path>` with its extension replaced by
+/// `manifest.json`.
+///
+/// `Config` (in `codex-rs/core/src/config.rs`, not part of this module) is
+/// expected to carry a `pub rollout_transforms: Vec<RolloutTransformConfig>`
+/// field, read by `RolloutRecorder::new`/`RolloutRecorder::fork` via
+/// [`load_transform_chain`] before handing it to `rollout_writer`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RolloutTransformConfig {
+    pub component_path: PathBuf,
+    #[serde(default)]
+    pub config: Value,
+}
+
+/// Loads every configured transform's manifest, compiles its component, and
+/// builds the chain that `RolloutRecorder::new` installs in front of the
+/// writer. Compilation is CPU-bound, so this is `async` and runs once at
+/// startup rather than being repeated on every record.
+pub async fn load_transform_chain(
+    configs: &[RolloutTransformConfig],
+) -> std::io::Result<RolloutTransformChain> {
+    let mut transforms: Vec<Box<dyn RolloutTransform>> = Vec::with_capacity(configs.len());
+    for entry in configs {
+        let manifest_path = entry.component_path.with_extension("manifest.json");
+        let manifest_json = std::fs::read_to_string(&manifest_path).map_err(|e| {
+            std::io::Error::other(format!(
+                "failed to read transform manifest {manifest_path:?}: {e}"
+            ))
+        })?;
+        let manifest: TransformManifest = serde_json::from_str(&manifest_json).map_err(|e| {
+            std::io::Error::other(format!("invalid transform manifest {manifest_path:?}: {e}"))
+        })?;
+        transforms.push(Box::new(
+            WasmRolloutTransform::new(entry.component_path.clone(), manifest, entry.config.clone())
+                .await?,
+        ));
+    }
+    Ok(RolloutTransformChain::new(transforms))
+}
+
+#[cfg(test)]
+mod tests {
+    use codex_protocol::models::ContentItem;
+
+    use super::*;
+
+    fn user_message(text: &str) -> RolloutItem {
+        RolloutItem::ResponseItem(codex_protocol::models::ResponseItem::Message {
+            id: None,
+            role: "user".to_string(),
+            content: vec![ContentItem::InputText {
+                text: text.to_string(),
+            }],
+        })
+    }
+
+    /// A transform that only declares interest in `record_types` and always
+    /// returns a fixed `TransformOutcome`, so the chain's filtering and
+    /// threading logic can be exercised without a real WASM component.
+    struct FixedTransform {
+        manifest: TransformManifest,
+        outcome: TransformOutcome,
+    }
+
+    #[async_trait::async_trait]
+    impl RolloutTransform for FixedTransform {
+        fn manifest(&self) -> &TransformManifest {
+            &self.manifest
+        }
+
+        async fn transform(&self, _item: &RolloutItem) -> std::io::Result<TransformOutcome> {
+            Ok(self.outcome.clone())
+        }
+    }
+
+    fn fixed(record_types: Vec<RolloutRecordKind>, outcome: TransformOutcome) -> Box<dyn RolloutTransform> {
+        Box::new(FixedTransform {
+            manifest: TransformManifest {
+                version: "0.0.0".to_string(),
+                record_types,
+                config_schema: None,
+            },
+            outcome,
+        })
+    }
+
+    #[tokio::test]
+    async fn transform_not_interested_in_the_record_kind_is_skipped() {
+        let chain = RolloutTransformChain::new(vec![fixed(vec![RolloutRecordKind::Event], TransformOutcome::Drop)]);
+
+        let result = chain.apply(user_message("hi")).await.unwrap();
+
+        assert!(result.is_some(), "a Response item should bypass an Event-only transform");
+    }
+
+    #[tokio::test]
+    async fn drop_removes_the_record_from_the_chain() {
+        let chain = RolloutTransformChain::new(vec![fixed(
+            vec![RolloutRecordKind::Response],
+            TransformOutcome::Drop,
+        )]);
+
+        let result = chain.apply(user_message("hi")).await.unwrap();
+
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn replace_threads_into_the_next_transform() {
+        let replacement = user_message("scrubbed");
+        let chain = RolloutTransformChain::new(vec![
+            fixed(
+                vec![RolloutRecordKind::Response],
+                TransformOutcome::Replace(replacement.clone()),
+            ),
+            fixed(vec![RolloutRecordKind::Response], TransformOutcome::Keep),
+        ]);
+
+        let result = chain.apply(user_message("hi")).await.unwrap().unwrap();
+
+        match result {
+            RolloutItem::ResponseItem(codex_protocol::models::ResponseItem::Message {
+                content,
+                ..
+            }) => {
+                assert_eq!(
+                    content,
+                    vec![ContentItem::InputText {
+                        text: "scrubbed".to_string()
+                    }]
+                );
+            }
+            other => panic!("expected the replacement message, got {other:?}"),
+        }
+    }
+}
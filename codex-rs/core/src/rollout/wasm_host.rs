@@ -0,0 +1,182 @@
+//! Sandboxed execution of rollout transform components.
+//!
+//! Every module is instantiated behind a `wasmtime` [`Linker`] wired up with
+//! the standard WASI interfaces (`wasmtime_wasi::add_to_linker_async`), but
+//! against a default WASI context: no preopened directories and no network
+//! access granted, so a transform can only see the record and config it is
+//! handed and return a result.
+//!
+//! Compiling a component is CPU-bound and, for any real module, far more
+//! expensive than running it, so [`CompiledTransform::compile`] does it once
+//! per configured transform (off the async executor, via `spawn_blocking`)
+//! and [`run_transform`] only pays for a fresh [`Store`] and instantiation on
+//! each call.
+//!
+//! This module (and [`super::transform`]) depend on `wasmtime`,
+//! `wasmtime-wasi`, and `async-trait`, which are not yet declared in
+//! `codex-rs/core/Cargo.toml`; `codex-rs/core/src/rollout/mod.rs` also needs
+//! `mod transform;`, `mod wasm_host;`, `mod index;`, `mod list;`,
+//! `mod merge;`, and `mod bench;` added alongside this file's sibling
+//! modules. Neither file is part of this module and so is left for whoever
+//! wires the `rollout` module into the rest of `codex-core`.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde_json::Value;
+use wasmtime::Config;
+use wasmtime::Engine;
+use wasmtime::Store;
+use wasmtime::component::Component;
+use wasmtime::component::Linker;
+use wasmtime_wasi::ResourceTable;
+use wasmtime_wasi::WasiCtx;
+use wasmtime_wasi::WasiCtxBuilder;
+use wasmtime_wasi::WasiView;
+
+use super::recorder::RolloutItem;
+use super::transform::TransformOutcome;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "snake_case", tag = "action")]
+enum WasmTransformResult {
+    Keep,
+    Replace { record: Value },
+    Drop,
+}
+
+/// A transform component compiled once and reused for every record it is
+/// shown, rather than being recompiled on each call.
+pub(crate) struct CompiledTransform {
+    engine: Engine,
+    component: Component,
+    linker: Linker<TransformCtx>,
+}
+
+impl CompiledTransform {
+    /// Compiles `component_path`. This does synchronous, CPU-bound work, so
+    /// callers on an async executor should run it via `spawn_blocking`.
+    pub(crate) fn compile(component_path: &Path) -> std::io::Result<Self> {
+        // `instantiate_async`/`call_async` (and `add_to_linker_async` below)
+        // require an engine built with `async_support` enabled — calling
+        // them against a `Config::default()` engine panics internally rather
+        // than returning an `Err`.
+        let mut config = Config::new();
+        config.async_support(true);
+        let engine = Engine::new(&config)
+            .map_err(|e| std::io::Error::other(format!("failed to create wasm engine: {e}")))?;
+        let component = Component::from_file(&engine, component_path)
+            .map_err(|e| std::io::Error::other(format!("failed to load transform component: {e}")))?;
+
+        let mut linker: Linker<TransformCtx> = Linker::new(&engine);
+        wasmtime_wasi::add_to_linker_async(&mut linker)
+            .map_err(|e| std::io::Error::other(format!("failed to wire up WASI: {e}")))?;
+
+        Ok(Self {
+            engine,
+            component,
+            linker,
+        })
+    }
+}
+
+/// Per-call WASI state. Denies network access and preopens nothing, so a
+/// transform only has clocks/random/stdio and the record it is handed.
+struct TransformCtx {
+    wasi: WasiCtx,
+    table: ResourceTable,
+}
+
+impl TransformCtx {
+    fn new() -> Self {
+        Self {
+            wasi: WasiCtxBuilder::new().build(),
+            table: ResourceTable::new(),
+        }
+    }
+}
+
+impl WasiView for TransformCtx {
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+}
+
+/// Runs `compiled`'s `transform` export on `item`, denying network access for
+/// the duration of the call.
+pub(crate) async fn run_transform(
+    compiled: &CompiledTransform,
+    config: &Value,
+    item: &RolloutItem,
+) -> std::io::Result<TransformOutcome> {
+    let record = rollout_item_to_value(item)?;
+
+    let mut store = Store::new(&compiled.engine, TransformCtx::new());
+    let instance = compiled
+        .linker
+        .instantiate_async(&mut store, &compiled.component)
+        .await
+        .map_err(|e| std::io::Error::other(format!("failed to instantiate transform: {e}")))?;
+
+    let transform_fn = instance
+        .get_typed_func::<(String, String), (String,)>(&mut store, "transform")
+        .map_err(|e| std::io::Error::other(format!("transform export not found: {e}")))?;
+
+    let record_json = serde_json::to_string(&record)?;
+    let config_json = serde_json::to_string(config)?;
+    let (result_json,) = transform_fn
+        .call_async(&mut store, (record_json, config_json))
+        .await
+        .map_err(|e| std::io::Error::other(format!("transform call failed: {e}")))?;
+
+    let result: WasmTransformResult = serde_json::from_str(&result_json)
+        .map_err(|e| std::io::Error::other(format!("invalid transform result: {e}")))?;
+
+    match result {
+        WasmTransformResult::Keep => Ok(TransformOutcome::Keep),
+        WasmTransformResult::Drop => Ok(TransformOutcome::Drop),
+        WasmTransformResult::Replace { record } => {
+            let item = value_to_rollout_item(item, record)?;
+            Ok(TransformOutcome::Replace(item))
+        }
+    }
+}
+
+/// Compiles `component_path` off the async executor, since component
+/// compilation is CPU-bound and can take tens of milliseconds for anything
+/// beyond a trivial module.
+pub(crate) async fn compile_transform(component_path: PathBuf) -> std::io::Result<CompiledTransform> {
+    tokio::task::spawn_blocking(move || CompiledTransform::compile(&component_path))
+        .await
+        .map_err(|e| std::io::Error::other(format!("transform compile task panicked: {e}")))?
+}
+
+fn rollout_item_to_value(item: &RolloutItem) -> std::io::Result<Value> {
+    match item {
+        RolloutItem::ResponseItem(item) => Ok(serde_json::to_value(item)?),
+        RolloutItem::Event(event) => Ok(serde_json::to_value(event)?),
+        RolloutItem::SessionMeta(meta) => Ok(serde_json::to_value(meta)?),
+    }
+}
+
+/// Rebuilds a [`RolloutItem`] of the same variant as `original` from the
+/// JSON a transform handed back, so a module cannot change a record's kind.
+fn value_to_rollout_item(original: &RolloutItem, value: Value) -> std::io::Result<RolloutItem> {
+    match original {
+        RolloutItem::ResponseItem(_) => Ok(RolloutItem::ResponseItem(
+            serde_json::from_value(value)
+                .map_err(|e| std::io::Error::other(format!("invalid response item: {e}")))?,
+        )),
+        RolloutItem::Event(_) => Ok(RolloutItem::Event(serde_json::from_value(value).map_err(
+            |e| std::io::Error::other(format!("invalid event: {e}")),
+        )?)),
+        RolloutItem::SessionMeta(_) => Ok(RolloutItem::SessionMeta(
+            serde_json::from_value(value)
+                .map_err(|e| std::io::Error::other(format!("invalid session meta: {e}")))?,
+        )),
+    }
+}